@@ -0,0 +1,339 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! The Fuchsia-specific executor, which multiplexes all of its waiting over a
+//! single Zircon `Port`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::Future;
+use futures::task::{self, ArcWake, Context, Poll};
+
+use zx;
+
+/// A trait for handling the receipt of a packet from a `zx::Port`.
+///
+/// Implementors are registered with an `EHandle` via `register_receiver`,
+/// which hands back the `u64` key that the kernel will echo back in every
+/// packet meant for this receiver.
+pub trait PacketReceiver: Send + Sync {
+    /// Receive a packet. Called by the executor's `port.wait` loop whenever a
+    /// packet with this receiver's key arrives.
+    fn receive_packet(&self, packet: zx::Packet);
+}
+
+/// A map from keys to `PacketReceiver`s, with monotonically increasing keys.
+/// A port can only ever see `u64::MAX` registrations over its lifetime, so
+/// the counter is never recycled.
+struct PacketReceiverMap<T> {
+    next_key: u64,
+    mapping: HashMap<u64, T>,
+}
+
+impl<T> PacketReceiverMap<T> {
+    fn new() -> Self {
+        PacketReceiverMap { next_key: 0, mapping: HashMap::new() }
+    }
+
+    fn insert(&mut self, receiver: T) -> u64 {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.mapping.insert(key, receiver);
+        key
+    }
+
+    fn get(&self, key: u64) -> Option<&T> {
+        self.mapping.get(&key)
+    }
+
+    fn remove(&mut self, key: u64) -> Option<T> {
+        self.mapping.remove(&key)
+    }
+}
+
+/// An entry in the executor's single, shared key space. Tasks and
+/// `PacketReceiver`s are registered in the same `PacketReceiverMap` (and so
+/// draw keys from the same counter) precisely so that a task and a receiver
+/// can never be handed the same key -- which `deliver_packet` has no way to
+/// disambiguate once a kernel packet has arrived.
+#[derive(Clone)]
+enum Entry {
+    Task(Arc<Task>),
+    Receiver(Arc<PacketReceiver>),
+}
+
+struct ExecutorInner {
+    port: zx::Port,
+    entries: Mutex<PacketReceiverMap<Entry>>,
+}
+
+/// A lightweight, `Clone`-able handle to an `Executor`'s port and receiver
+/// table. `EHandle` is what `Channel`, `Socket`, `RWHandle` and friends hold
+/// on to so that they can register signal interest without owning the
+/// executor itself.
+#[derive(Clone)]
+pub struct EHandle {
+    inner: Arc<ExecutorInner>,
+}
+
+thread_local!(
+    static LOCAL_EHANDLE: RefCell<Option<EHandle>> = RefCell::new(None)
+);
+
+impl EHandle {
+    /// The `EHandle` for the thread's currently-running `Executor`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a `run_singlethreaded` call.
+    pub fn local() -> Self {
+        LOCAL_EHANDLE.with(|e| {
+            e.borrow().clone().expect("no executor is running on this thread")
+        })
+    }
+
+    /// The `zx::Port` backing this executor. All waits registered through
+    /// `register_receiver` are woken from this single port.
+    pub fn port(&self) -> &zx::Port {
+        &self.inner.port
+    }
+
+    /// Register a `PacketReceiver`, returning a registration that owns its
+    /// key and deregisters it on drop.
+    pub fn register_receiver<T>(&self, receiver: Arc<T>) -> ReceiverRegistration<T>
+    where
+        T: PacketReceiver + 'static,
+    {
+        let key = {
+            let dyn_receiver = receiver.clone() as Arc<PacketReceiver>;
+            self.inner.entries.lock().unwrap().insert(Entry::Receiver(dyn_receiver))
+        };
+        ReceiverRegistration { ehandle: self.clone(), key, receiver }
+    }
+
+    /// Remove a previously registered receiver. Called automatically when a
+    /// `ReceiverRegistration` is dropped.
+    pub fn deregister_receiver(&self, key: u64) {
+        self.inner.entries.lock().unwrap().remove(key);
+    }
+
+    fn deliver_packet(&self, key: u64, packet: zx::Packet) {
+        // The lookup's `MutexGuard` is dropped before acting on the result:
+        // `task.run()` may re-lock `entries` itself if the future it polls
+        // completes, and keeping the guard alive across the call would
+        // deadlock the executor.
+        let entry = self.inner.entries.lock().unwrap().get(key).cloned();
+        match entry {
+            Some(Entry::Task(task)) => task.run(),
+            Some(Entry::Receiver(receiver)) => receiver.receive_packet(packet),
+            None => {} // Unknown key: raced with a cancel. Drop the packet.
+        }
+    }
+
+    fn spawn(&self, future: Box<Future<Output = ()> + Send>) {
+        let task = Arc::new(Task {
+            ehandle: self.clone(),
+            key: AtomicU64::new(0),
+            future: Mutex::new(Some(future)),
+            woken: AtomicBool::new(true),
+        });
+        let key = self.inner.entries.lock().unwrap().insert(Entry::Task(task.clone()));
+        task.key.store(key, Ordering::SeqCst);
+        task.run();
+    }
+}
+
+/// A registration of a `PacketReceiver` against an `EHandle`'s port. Keeps
+/// the receiver alive and deregisters it when dropped.
+pub struct ReceiverRegistration<T> {
+    receiver: Arc<T>,
+    ehandle: EHandle,
+    key: u64,
+}
+
+impl<T> ReceiverRegistration<T> {
+    /// The key under which this receiver is registered with the port.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The port this receiver is registered on.
+    pub fn port(&self) -> &zx::Port {
+        self.ehandle.port()
+    }
+}
+
+impl<T> Deref for ReceiverRegistration<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.receiver
+    }
+}
+
+impl<T> Drop for ReceiverRegistration<T> {
+    fn drop(&mut self) {
+        self.ehandle.deregister_receiver(self.key);
+    }
+}
+
+/// A spawned future, registered under its own key in the executor's entry
+/// map so that waking it is just a matter of queueing a user packet with its
+/// key back onto the executor's port.
+struct Task {
+    ehandle: EHandle,
+    key: AtomicU64,
+    future: Mutex<Option<Box<Future<Output = ()> + Send>>>,
+    woken: AtomicBool,
+}
+
+impl Task {
+    /// Poll the future if a wake is outstanding. Called both the first time
+    /// the task is spawned and every time a user packet with its key
+    /// subsequently arrives on the port.
+    fn run(self: Arc<Self>) {
+        if !self.woken.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let mut slot = self.future.lock().unwrap();
+        let done = if let Some(future) = slot.as_mut() {
+            let waker = task::waker(self.clone());
+            let mut cx = Context::from_waker(&waker);
+            future.as_mut().poll(&mut cx).is_ready()
+        } else {
+            true
+        };
+        if done {
+            *slot = None;
+            self.ehandle.inner.entries.lock().unwrap().remove(self.key.load(Ordering::SeqCst));
+        }
+    }
+}
+
+impl ArcWake for Task {
+    fn wake(arc_self: &Arc<Self>) {
+        // Coalesce redundant wakes into a single outstanding packet.
+        if arc_self.woken.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let key = arc_self.key.load(Ordering::SeqCst);
+        let _ = arc_self.ehandle.port().queue(&zx::Packet::from_user_packet(
+            key, 0, zx::UserPacket::from_u8_array([0; 32]),
+        ));
+    }
+}
+
+/// A single-threaded futures executor for Fuchsia, backed by a `zx::Port`
+/// that all waiting handles and timers share.
+pub struct Executor {
+    ehandle: EHandle,
+}
+
+impl Executor {
+    /// Create a new executor, backed by a freshly-created `zx::Port`.
+    pub fn new() -> Result<Self, zx::Status> {
+        Ok(Executor {
+            ehandle: EHandle {
+                inner: Arc::new(ExecutorInner {
+                    port: zx::Port::create()?,
+                    entries: Mutex::new(PacketReceiverMap::new()),
+                }),
+            },
+        })
+    }
+
+    /// A handle to this executor's port and receiver table.
+    pub fn ehandle(&self) -> EHandle {
+        self.ehandle.clone()
+    }
+
+    /// Run a single future to completion on this thread, servicing all
+    /// spawned tasks and registered packet receivers until it does.
+    ///
+    /// Unlike `spawn`/`spawn_local`, the top-level future is polled directly
+    /// rather than boxed into a `Task`, so it need not be `Send` or `'static`.
+    pub fn run_singlethreaded<F>(&mut self, mut main_future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let ehandle = self.ehandle.clone();
+        LOCAL_EHANDLE.with(|e| *e.borrow_mut() = Some(ehandle.clone()));
+
+        let waker = task::waker(Arc::new(MainWaker { ehandle: ehandle.clone() }));
+        let mut cx = Context::from_waker(&waker);
+        // Safe because `main_future` is a local that is never moved again.
+        let mut main_future = unsafe { Pin::new_unchecked(&mut main_future) };
+
+        loop {
+            if let Poll::Ready(result) = main_future.as_mut().poll(&mut cx) {
+                LOCAL_EHANDLE.with(|e| *e.borrow_mut() = None);
+                return result;
+            }
+            let packet = ehandle.inner.port.wait(zx::Time::INFINITE)
+                .expect("failed to wait on executor port");
+            if packet.key() == MAIN_TASK_KEY {
+                continue;
+            }
+            ehandle.deliver_packet(packet.key(), packet);
+        }
+    }
+}
+
+/// Reserved key for packets that merely wake the top-level future polled by
+/// `run_singlethreaded`. `PacketReceiverMap` counts up from zero, so the top
+/// of the key space is never handed out to a real registration.
+const MAIN_TASK_KEY: u64 = ::std::u64::MAX;
+
+/// A waker for the future passed directly to `run_singlethreaded`. It has no
+/// access to the future itself -- only enough to re-queue a packet -- so it
+/// can be `Send + Sync` even when the future it represents is not.
+struct MainWaker {
+    ehandle: EHandle,
+}
+
+impl ArcWake for MainWaker {
+    fn wake(arc_self: &Arc<Self>) {
+        let _ = arc_self.ehandle.port().queue(&zx::Packet::from_user_packet(
+            MAIN_TASK_KEY, 0, zx::UserPacket::from_u8_array([0; 32]),
+        ));
+    }
+}
+
+/// Spawn a new task to be run on the thread-local executor.
+///
+/// # Panics
+///
+/// Panics if called outside of a `run_singlethreaded` call.
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    EHandle::local().spawn(Box::new(future));
+}
+
+/// Spawn a new non-`Send` task to be run on the thread-local executor.
+///
+/// # Panics
+///
+/// Panics if called outside of a `run_singlethreaded` call.
+pub fn spawn_local<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    // The executor only ever runs tasks on the thread that spawned them, so
+    // it's sound to pretend a thread-bound future is `Send`.
+    struct AssertSend<F>(F);
+    unsafe impl<F> Send for AssertSend<F> {}
+    impl<F: Future> Future for AssertSend<F> {
+        type Output = F::Output;
+        fn poll(self: ::std::pin::Pin<&mut Self>, cx: &mut Context) -> task::Poll<F::Output> {
+            unsafe { self.map_unchecked_mut(|s| &mut s.0) }.poll(cx)
+        }
+    }
+    EHandle::local().spawn(Box::new(AssertSend(future)));
+}
@@ -0,0 +1,223 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A C ABI over the executor's port-based wait machinery, so that non-Rust
+//! components can register asynchronous waits without owning the futures
+//! runtime themselves.
+
+use std::sync::{Arc, Mutex};
+
+use zx;
+use zx::AsHandleRef;
+use zx::sys::{zx_handle_t, zx_packet_signal_t, zx_signals_t, zx_status_t};
+
+use executor::{EHandle, PacketReceiver, ReceiverRegistration};
+
+/// C callback invoked when an `async_wait_t`'s wait completes. `status` is
+/// always `ZX_OK`: a failed `zx_object_wait_async` is reported through
+/// `async_begin_wait`'s return value, and the callback is not invoked.
+pub type async_wait_handler_t =
+    extern "C" fn(wait: *mut async_wait_t, status: zx_status_t, signal: *const zx_packet_signal_t);
+
+/// A single asynchronous wait, registered against a running `Executor`'s
+/// port. Callers allocate this struct and must not move or free it while a
+/// wait is outstanding.
+#[repr(C)]
+pub struct async_wait_t {
+    object: zx_handle_t,
+    trigger: zx_signals_t,
+    handler: async_wait_handler_t,
+    state: Mutex<WaitState>,
+}
+
+/// The states a wait passes through, normally `Unregistered -> Registered ->
+/// Finished`. The only subtlety is the kernel satisfying the wait, and the
+/// executor's port loop dispatching the resulting packet, before
+/// `async_begin_wait` has finished recording `Registered` -- the dispatch
+/// path then stashes the packet as `EarlyPacket` instead of firing the
+/// callback, and `async_begin_wait` fires it once it takes the lock.
+enum WaitState {
+    Unregistered,
+    Registered(ReceiverRegistration<WaitReceiver>),
+    EarlyPacket(zx_packet_signal_t),
+    Finished,
+}
+
+impl async_wait_t {
+    /// Construct a new, unregistered wait for `object` becoming `trigger`.
+    pub fn new(object: zx_handle_t, trigger: zx_signals_t, handler: async_wait_handler_t) -> Self {
+        async_wait_t { object, trigger, handler, state: Mutex::new(WaitState::Unregistered) }
+    }
+}
+
+struct WaitReceiver {
+    wait: *mut async_wait_t,
+}
+
+// `wait` is read only through `async_wait_t::state`'s own `Mutex`, so the
+// raw pointer is safe to hand to the executor's dispatch thread.
+unsafe impl Send for WaitReceiver {}
+unsafe impl Sync for WaitReceiver {}
+
+impl PacketReceiver for WaitReceiver {
+    fn receive_packet(&self, packet: zx::Packet) {
+        let raw = match packet.contents() {
+            zx::PacketContents::SignalOne(signal) => zx_packet_signal_t {
+                trigger: signal.trigger().bits(),
+                observed: signal.observed().bits(),
+                count: signal.count(),
+            },
+            _ => return,
+        };
+        // Safe: the caller keeps `wait` alive for as long as this receiver
+        // is registered, which ends exactly when `wait` is torn down.
+        let wait = unsafe { &*self.wait };
+        let mut state = wait.state.lock().unwrap();
+        match *state {
+            WaitState::Registered(_) => {
+                *state = WaitState::Finished;
+                drop(state);
+                (wait.handler)(wait as *const _ as *mut _, zx::sys::ZX_OK, &raw as *const _);
+            }
+            WaitState::Unregistered => {
+                // `async_begin_wait` hasn't recorded `Registered` yet; it
+                // will fire the callback itself once it observes this.
+                *state = WaitState::EarlyPacket(raw);
+            }
+            WaitState::EarlyPacket(_) | WaitState::Finished => {
+                // Shouldn't happen: the wait is one-shot and we never
+                // re-arm it while a packet could still be outstanding.
+            }
+        }
+    }
+}
+
+/// Begin a wait on `wait->object` becoming `wait->trigger` on `executor`'s
+/// port. `wait` must remain valid (and must not be moved) until its callback
+/// fires or `async_cancel_wait` is called.
+#[no_mangle]
+pub unsafe extern "C" fn async_begin_wait(executor: &EHandle, wait: *mut async_wait_t) -> zx_status_t {
+    let object = (*wait).object;
+    let trigger = (*wait).trigger;
+
+    let registration = executor.register_receiver(Arc::new(WaitReceiver { wait }));
+    let key = registration.key();
+
+    let status = zx::sys::zx_object_wait_async(
+        object, registration.port().raw_handle(), key, trigger, zx::sys::ZX_WAIT_ASYNC_ONCE,
+    );
+    if status != zx::sys::ZX_OK {
+        return status;
+    }
+
+    let mut state = (*wait).state.lock().unwrap();
+    match ::std::mem::replace(&mut *state, WaitState::Registered(registration)) {
+        WaitState::EarlyPacket(raw) => {
+            *state = WaitState::Finished;
+            drop(state);
+            // The packet beat us to the lock; fire the callback ourselves.
+            ((*wait).handler)(wait, zx::sys::ZX_OK, &raw as *const _);
+        }
+        WaitState::Unregistered => {}
+        WaitState::Registered(_) | WaitState::Finished => {
+            unreachable!("async_begin_wait called twice on the same wait")
+        }
+    }
+
+    zx::sys::ZX_OK
+}
+
+/// Cancel a wait begun with `async_begin_wait`. Idempotent: safe to call
+/// regardless of whether the wait is still pending, has already fired, or
+/// was never begun.
+#[no_mangle]
+pub unsafe extern "C" fn async_cancel_wait(_executor: &EHandle, wait: *mut async_wait_t) -> zx_status_t {
+    let mut state = (*wait).state.lock().unwrap();
+    // Dropping a `Registered` state's `ReceiverRegistration` deregisters it
+    // from the executor; we additionally cancel the outstanding kernel wait
+    // so no stray packet for this key arrives afterward.
+    if let WaitState::Registered(registration) = &*state {
+        let port = registration.port();
+        let _ = zx::sys::zx_port_cancel(port.raw_handle(), (*wait).object, registration.key());
+    }
+    *state = WaitState::Finished;
+    zx::sys::ZX_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use zx::AsHandleRef;
+    use executor::Executor;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn record_call(_wait: *mut async_wait_t, status: zx_status_t, _signal: *const zx_packet_signal_t) {
+        assert_eq!(status, zx::sys::ZX_OK);
+        CALLS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn begin_wait_fires_callback_on_signal() {
+        CALLS.store(0, Ordering::SeqCst);
+        let executor = Executor::new().unwrap();
+        let ehandle = executor.ehandle();
+        let event = zx::Event::create().unwrap();
+
+        let mut wait = async_wait_t::new(event.raw_handle(), zx::Signals::USER_0.bits(), record_call);
+        assert_eq!(unsafe { async_begin_wait(&ehandle, &mut wait) }, zx::sys::ZX_OK);
+
+        assert!(event.signal_handle(zx::Signals::NONE, zx::Signals::USER_0).is_ok());
+
+        let packet = ehandle.port().wait(zx::Time::INFINITE).unwrap();
+        // Check the state under the lock, then release it before delivering:
+        // `receive_packet` takes the same (non-reentrant) lock itself.
+        match *wait.state.lock().unwrap() {
+            WaitState::Registered(_) => {}
+            _ => panic!("expected Registered state before the packet is delivered"),
+        }
+        WaitReceiver { wait: &mut wait }.receive_packet(packet);
+
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        assert!(match *wait.state.lock().unwrap() { WaitState::Finished => true, _ => false });
+    }
+
+    /// Exercises the race the backlog calls out as the hard part: the
+    /// kernel packet is dispatched to the receiver *before*
+    /// `async_begin_wait` has recorded `Registered`. Rather than relying on
+    /// genuine thread interleaving (which would make this test flaky), the
+    /// early dispatch is simulated directly against a still-`Unregistered`
+    /// wait, then `async_begin_wait` is driven through its normal path to
+    /// confirm it notices the stashed packet and fires the callback itself.
+    #[test]
+    fn dispatch_racing_ahead_of_registration_fires_callback_from_begin_wait() {
+        CALLS.store(0, Ordering::SeqCst);
+        let executor = Executor::new().unwrap();
+        let ehandle = executor.ehandle();
+        let event = zx::Event::create().unwrap();
+
+        let mut wait = async_wait_t::new(event.raw_handle(), zx::Signals::USER_0.bits(), record_call);
+
+        // Get a genuine signal packet off a throwaway wait_async, independent
+        // of the one `async_begin_wait` will issue below.
+        assert!(event
+            .wait_async_handle(ehandle.port(), 9999, zx::Signals::USER_0, zx::WaitAsyncOpts::Once)
+            .is_ok());
+        assert!(event.signal_handle(zx::Signals::NONE, zx::Signals::USER_0).is_ok());
+        let packet = ehandle.port().wait(zx::Time::INFINITE).unwrap();
+
+        // `wait` is still `Unregistered`: this is what the dispatch path
+        // sees if it wins the race against `async_begin_wait`.
+        WaitReceiver { wait: &mut wait }.receive_packet(packet);
+        assert!(match *wait.state.lock().unwrap() { WaitState::EarlyPacket(_) => true, _ => false });
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0, "the callback must not fire before registration");
+
+        assert_eq!(unsafe { async_begin_wait(&ehandle, &mut wait) }, zx::sys::ZX_OK);
+
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1, "begin_wait must fire the stashed packet's callback");
+        assert!(match *wait.state.lock().unwrap() { WaitState::Finished => true, _ => false });
+    }
+}
@@ -11,6 +11,11 @@
 // Set the system allocator for anything using this crate
 extern crate fuchsia_system_alloc;
 
+extern crate fuchsia_zircon as zx;
+// Re-exported so that `many_futures!`, invoked by downstream crates, can
+// refer to `$crate::futures` without them needing their own `futures` dep.
+pub extern crate futures;
+
 /// A future which can be used by multiple threads at once.
 pub mod atomic_future;
 
@@ -26,6 +31,8 @@ mod timer;
 pub use self::timer::{Interval, Timer, TimeoutExt, OnTimeout};
 mod executor;
 pub use self::executor::{Executor, EHandle, spawn, spawn_local};
+/// A C ABI over the executor's wait machinery, for non-Rust callers.
+pub mod ffi;
 mod fifo;
 pub use self::fifo::{Fifo, FifoEntry, FifoReadable, FifoWritable, ReadEntry, WriteEntry};
 pub mod net;
@@ -0,0 +1,127 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! A generic wrapper around a signal-bearing Zircon handle, providing a
+//! `poll_signal` poll helper that `Channel` and `Socket` are built on top of.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use futures::task::{AtomicWaker, Context, Poll};
+
+use zx;
+use zx::AsHandleRef;
+
+use executor::{EHandle, PacketReceiver, ReceiverRegistration};
+
+/// A `Send`-able handle wrapper that multiplexes signal waits for a single
+/// Zircon object through the executor's shared port, rather than issuing one
+/// `wait_async` call per `poll`.
+pub struct RWHandle<T: AsHandleRef> {
+    object: T,
+    receiver: ReceiverRegistration<RWPacketReceiver>,
+}
+
+impl<T: AsHandleRef> RWHandle<T> {
+    /// Wrap `object`, registering it with the thread-local executor.
+    pub fn new(object: T) -> Self {
+        let ehandle = EHandle::local();
+        let receiver = ehandle.register_receiver(::std::sync::Arc::new(RWPacketReceiver {
+            signals: AtomicU32::new(0),
+            outstanding: AtomicU32::new(0),
+            read_waker: AtomicWaker::new(),
+            write_waker: AtomicWaker::new(),
+        }));
+        RWHandle { object, receiver }
+    }
+
+    /// The underlying handle.
+    pub fn get_ref(&self) -> &T {
+        &self.object
+    }
+
+    /// The key this handle is registered under on the executor's port.
+    pub fn key(&self) -> u64 {
+        self.receiver.key()
+    }
+
+    /// Unwraps this `RWHandle`, returning the underlying handle.
+    pub fn into_inner(self) -> T {
+        self.object
+    }
+
+    /// Polls for the given `signal` becoming asserted on the underlying
+    /// object, registering `cx`'s waker to be woken when it arrives.
+    ///
+    /// `OBJECT_PEER_CLOSED` is treated as always "ready": once the peer has
+    /// closed, no further `wait_async` calls will ever complete, but there
+    /// may still be buffered data to read, so callers must not mistake this
+    /// for EOF on its own.
+    pub fn poll_signal(&self, signal: zx::Signals, cx: &mut Context) -> Poll<Result<(), zx::Status>> {
+        let waker = if signal.contains(zx::Signals::OBJECT_WRITABLE) {
+            &self.receiver.write_waker
+        } else {
+            &self.receiver.read_waker
+        };
+        waker.register(cx.waker());
+
+        // Clear the bits we're about to wait on: a signal we've already
+        // consumed shouldn't look "still asserted" to the next poll.
+        let cleared = self.receiver.signals.fetch_and(!signal.bits(), Ordering::SeqCst);
+        let observed = zx::Signals::from_bits_truncate(cleared);
+
+        if observed.contains(zx::Signals::OBJECT_PEER_CLOSED) {
+            return Poll::Ready(Ok(()));
+        } else if observed.intersects(signal) {
+            // The bit was set: a previous `wait_async` already fired and
+            // latched it, so the signal really is asserted right now.
+            return Poll::Ready(Ok(()));
+        }
+
+        // The signal hasn't been observed yet. Track "a wait_async is in
+        // flight for these bits" explicitly, rather than inferring it from
+        // `signals`: with only the observed-signals bit to go on, a second
+        // `poll_signal` landing before the first `Once` wait has fired would
+        // look identical to "never asked" and double-register the same
+        // port/key pair.
+        let registration_signals = signal | zx::Signals::OBJECT_PEER_CLOSED;
+        let previously_outstanding =
+            self.receiver.outstanding.fetch_or(registration_signals.bits(), Ordering::SeqCst);
+        if !zx::Signals::from_bits_truncate(previously_outstanding).intersects(signal) {
+            self.object.wait_async_handle(
+                self.receiver.port(),
+                self.key(),
+                registration_signals,
+                zx::WaitAsyncOpts::Once,
+            )?;
+        }
+
+        Poll::Pending
+    }
+}
+
+struct RWPacketReceiver {
+    /// Signal bits that have been observed (via a completed `wait_async`)
+    /// but not yet consumed by a matching `poll_signal` call.
+    signals: AtomicU32,
+    /// Signal bits for which a `wait_async` `Once` wait is currently in
+    /// flight, so `poll_signal` doesn't issue a redundant one.
+    outstanding: AtomicU32,
+    read_waker: AtomicWaker,
+    write_waker: AtomicWaker,
+}
+
+impl PacketReceiver for RWPacketReceiver {
+    fn receive_packet(&self, packet: zx::Packet) {
+        let (trigger, observed) = match packet.contents() {
+            zx::PacketContents::SignalOne(signal) => (signal.trigger(), signal.observed()),
+            _ => return,
+        };
+        // The wait that was registered for these bits has now fired, so they're
+        // no longer "in flight" -- a future `poll_signal` is free to re-arm.
+        self.outstanding.fetch_and(!trigger.bits(), Ordering::SeqCst);
+        self.signals.fetch_or(observed.bits(), Ordering::SeqCst);
+        self.read_waker.wake();
+        self.write_waker.wake();
+    }
+}
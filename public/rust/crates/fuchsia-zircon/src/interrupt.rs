@@ -0,0 +1,32 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe bindings for Zircon interrupt objects.
+
+use {AsHandleRef, HandleBased, Handle, Port, Status};
+use {sys, ok};
+
+/// An object representing a Zircon
+/// [interrupt](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/interrupt.md).
+///
+/// As essentially a subtype of `Handle`, it can be freely interconverted.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Interrupt(Handle);
+impl_handle_based!(Interrupt);
+
+impl Interrupt {
+    /// Binds the interrupt to `port`, so that future interrupt triggers are delivered as
+    /// `ZX_PKT_TYPE_INTERRUPT` packets with the given `key`, rather than requiring a dedicated
+    /// `wait` thread per interrupt.
+    ///
+    /// Wraps the
+    /// [zx_interrupt_bind](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/interrupt_bind.md)
+    /// syscall.
+    pub fn bind(&self, port: &Port, key: u64, options: u32) -> Result<(), Status> {
+        let status = unsafe {
+            sys::zx_interrupt_bind(self.raw_handle(), port.raw_handle(), key, options)
+        };
+        ok(status)
+    }
+}
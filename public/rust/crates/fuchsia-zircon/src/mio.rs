@@ -0,0 +1,182 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! An optional `mio::Evented` implementation for Zircon handles, so that
+//! existing mio-based networking stacks can drive Fuchsia handles through an
+//! `mio::Poll` the same way they drive sockets on other platforms.
+//!
+//! This module is only compiled in when the `mio` feature is enabled.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+
+use mio::{self, Evented, Poll, PollOpt, Ready, Token};
+
+use {AsHandleRef, HandleRef, Packet, PacketContents, Port};
+use {Signals, Status, Time, WaitAsyncOpts};
+
+// The low bits of `Ready::as_usize()` that `readable`/`writable` occupy;
+// everything above this is free for us to stash user-defined signal bits in,
+// so callers can wait on object-specific signals mio has no native concept
+// of and recover them with `Ready::from_usize`.
+const READY_SIGNAL_MASK: usize = 0b11;
+
+/// Translate a requested mio `Ready` set into the `Signals` to pass to
+/// `wait_async`.
+fn ready_to_signals(ready: Ready) -> Signals {
+    let mut signals = Signals::NONE;
+    if ready.is_readable() {
+        signals |= Signals::OBJECT_READABLE | Signals::OBJECT_PEER_CLOSED;
+    }
+    if ready.is_writable() {
+        signals |= Signals::OBJECT_WRITABLE;
+    }
+    signals | Signals::from_bits_truncate((ready.as_usize() & !READY_SIGNAL_MASK) as u32)
+}
+
+/// Translate an observed `Signals` set into the mio `Ready` it corresponds
+/// to, preserving any bits outside readable/writable/peer-closed.
+fn signals_to_ready(signals: Signals) -> Ready {
+    let mut ready = Ready::empty();
+    if signals.contains(Signals::OBJECT_READABLE) || signals.contains(Signals::OBJECT_PEER_CLOSED) {
+        ready |= Ready::readable();
+    }
+    if signals.contains(Signals::OBJECT_WRITABLE) {
+        ready |= Ready::writable();
+    }
+    let extra = signals.bits() as usize & !READY_SIGNAL_MASK;
+    Ready::from_usize(ready.as_usize() | extra)
+}
+
+/// The single `zx::Port` and background thread shared by every
+/// `EventedHandle` in the process, so that registering many handles drives
+/// them through one reactor rather than spinning up a thread per
+/// registration -- the latter defeats the point of handing mio-based
+/// networking stacks a single thing to poll.
+struct Pump {
+    port: Port,
+    next_key: AtomicU64,
+    readiness: Mutex<HashMap<u64, mio::SetReadiness>>,
+}
+
+impl Pump {
+    fn next_key(&self) -> u64 {
+        self.next_key.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// The process-wide `Pump`, lazily created by the first `EventedHandle` to be
+/// registered and never torn down afterward.
+fn pump() -> &'static Pump {
+    static INIT: Once = Once::new();
+    static mut PUMP: *const Pump = 0 as *const Pump;
+    unsafe {
+        INIT.call_once(|| {
+            let port = Port::create().expect("failed to create the shared mio pump port");
+            let pump = Box::leak(Box::new(Pump {
+                port,
+                next_key: AtomicU64::new(0),
+                readiness: Mutex::new(HashMap::new()),
+            }));
+            PUMP = pump as *const Pump;
+            thread::spawn(move || pump_loop(pump));
+        });
+        &*PUMP
+    }
+}
+
+/// Forwards every signal packet arriving on `pump`'s port to whichever
+/// `EventedHandle` registered under that packet's key, by way of its stashed
+/// `SetReadiness`. Runs for the lifetime of the process.
+fn pump_loop(pump: &'static Pump) {
+    loop {
+        match pump.port.wait(Time::INFINITE) {
+            Ok(packet) => {
+                if let PacketContents::SignalOne(signal) = packet.contents() {
+                    let set_readiness = pump.readiness.lock().unwrap().get(&packet.key()).cloned();
+                    if let Some(set_readiness) = set_readiness {
+                        let _ = set_readiness.set_readiness(signals_to_ready(signal.observed()));
+                    }
+                }
+            }
+            Err(_) => break, // The shared port has gone away; nothing left to pump.
+        }
+    }
+}
+
+struct Inner {
+    registration: mio::Registration,
+    key: u64,
+}
+
+/// Wraps a Zircon handle so it can be registered with an `mio::Poll`.
+///
+/// All `EventedHandle`s share one `Pump` (a single port and background
+/// thread, see above); each registration is distinguished only by the `u64`
+/// key it's assigned when `register` is first called. That key -- not
+/// mio's `Token`, which `reregister` may change -- is what every later
+/// `wait_async`/`cancel` call against the shared port uses.
+pub struct EventedHandle<'a> {
+    handle: HandleRef<'a>,
+    inner: Mutex<Option<Inner>>,
+}
+
+impl<'a> EventedHandle<'a> {
+    /// Wrap `handle` for use with mio. Nothing is registered with the shared
+    /// pump's port until `register` is called.
+    pub fn new(handle: HandleRef<'a>) -> Self {
+        EventedHandle { handle, inner: Mutex::new(None) }
+    }
+
+    fn wait_async(&self, key: u64, interest: Ready) -> io::Result<()> {
+        self.handle
+            .wait_async_handle(&pump().port, key, ready_to_signals(interest), WaitAsyncOpts::Once)
+            .map_err(status_to_io)
+    }
+}
+
+impl<'a> Evented for EventedHandle<'a> {
+    fn register(&self, poll: &Poll, token: Token, interest: Ready, _opts: PollOpt) -> io::Result<()> {
+        let (registration, set_readiness) = mio::Registration::new2();
+        poll.register(&registration, token, interest, PollOpt::edge())?;
+
+        let key = pump().next_key();
+        pump().readiness.lock().unwrap().insert(key, set_readiness);
+        self.wait_async(key, interest)?;
+
+        *self.inner.lock().unwrap() = Some(Inner { registration, key });
+        Ok(())
+    }
+
+    fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        let guard = self.inner.lock().unwrap();
+        let inner = guard.as_ref().ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound, "reregister called before register",
+        ))?;
+        poll.reregister(&inner.registration, token, interest, opts)?;
+        // The previous `wait_async` was one-shot and has already fired (that
+        // is how mio came to call us again); a fresh one picks up the new
+        // interest. It's re-armed under the same key this handle was
+        // assigned in `register` -- that key, not the token, is what
+        // `deregister` later cancels, so it must never change underneath it.
+        self.wait_async(inner.key, interest)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        if let Some(inner) = self.inner.lock().unwrap().take() {
+            let _ = pump().port.cancel(&self.handle, inner.key);
+            pump().readiness.lock().unwrap().remove(&inner.key);
+            poll.deregister(&inner.registration)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn status_to_io(status: Status) -> io::Error {
+    io::Error::from_raw_os_error(status.into_raw())
+}
@@ -31,6 +31,16 @@ pub enum PacketContents {
     SignalOne(SignalPacket),
     /// A repeating signal packet generated via `object_wait_async`.
     SignalRep(SignalPacket),
+    /// A packet generated by an interrupt through `Interrupt::bind`.
+    Interrupt(InterruptPacket),
+    /// A packet generated by a guest triggering a virtual bell trap.
+    GuestBell(GuestBellPacket),
+    /// A packet generated by a guest faulting on an emulated memory region.
+    GuestMem(GuestMemPacket),
+    /// A packet generated by a guest faulting on an emulated IO port.
+    GuestIo(GuestIoPacket),
+    /// A packet generated by a guest VCPU, e.g. on startup or interrupt.
+    GuestVcpu(GuestVcpuPacket),
 
     #[doc(hidden)]
     __Nonexhaustive
@@ -46,6 +56,72 @@ pub struct UserPacket(sys::zx_packet_user_t);
 #[derive(Debug, Copy, Clone)]
 pub struct SignalPacket(sys::zx_packet_signal_t);
 
+/// Contents of an interrupt packet (one generated by `Interrupt::bind`). This is a type-safe
+/// wrapper for
+/// [zx_packet_interrupt_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait.md).
+#[derive(Debug, Copy, Clone)]
+pub struct InterruptPacket(sys::zx_packet_interrupt_t);
+
+impl InterruptPacket {
+    /// The timestamp at which the interrupt was triggered.
+    pub fn timestamp(&self) -> Time {
+        Time::from_nanos(self.0.timestamp)
+    }
+}
+
+/// Contents of a guest bell trap packet. This is a type-safe wrapper for
+/// [zx_packet_guest_bell_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait.md).
+#[derive(Debug, Copy, Clone)]
+pub struct GuestBellPacket(sys::zx_packet_guest_bell_t);
+
+impl GuestBellPacket {
+    /// The guest-physical address of the bell trap that was triggered.
+    pub fn addr(&self) -> u64 {
+        self.0.addr
+    }
+}
+
+/// Contents of a guest memory trap packet. This is a type-safe wrapper for
+/// [zx_packet_guest_mem_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait.md).
+#[derive(Debug, Copy, Clone)]
+pub struct GuestMemPacket(sys::zx_packet_guest_mem_t);
+
+impl GuestMemPacket {
+    /// The guest-physical address of the memory access that was trapped.
+    pub fn addr(&self) -> u64 {
+        self.0.addr
+    }
+}
+
+/// Contents of a guest IO trap packet. This is a type-safe wrapper for
+/// [zx_packet_guest_io_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait.md).
+#[derive(Debug, Copy, Clone)]
+pub struct GuestIoPacket(sys::zx_packet_guest_io_t);
+
+impl GuestIoPacket {
+    /// The IO port that was trapped.
+    pub fn port(&self) -> u16 {
+        self.0.port
+    }
+
+    /// Whether the trapped access was an input (as opposed to an output).
+    pub fn input(&self) -> bool {
+        self.0.input
+    }
+}
+
+/// Contents of a guest VCPU packet. This is a type-safe wrapper for
+/// [zx_packet_guest_vcpu_t](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_wait.md).
+#[derive(Debug, Copy, Clone)]
+pub struct GuestVcpuPacket(sys::zx_packet_guest_vcpu_t);
+
+impl GuestVcpuPacket {
+    /// The packet's kernel-defined type (e.g. interrupt vs. startup).
+    pub fn kind(&self) -> u8 {
+        self.0.kind
+    }
+}
+
 impl Packet {
     /// Creates a new packet with `UserPacket` data.
     pub fn from_user_packet(key: u64, status: i32, user: UserPacket) -> Packet {
@@ -78,6 +154,16 @@ impl Packet {
             PacketContents::SignalOne(SignalPacket(unsafe { mem::transmute_copy(&self.0.union) }))
         } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_SIGNAL_REP {
             PacketContents::SignalRep(SignalPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_INTERRUPT {
+            PacketContents::Interrupt(InterruptPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_GUEST_BELL {
+            PacketContents::GuestBell(GuestBellPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_GUEST_MEM {
+            PacketContents::GuestMem(GuestMemPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_GUEST_IO {
+            PacketContents::GuestIo(GuestIoPacket(unsafe { mem::transmute_copy(&self.0.union) }))
+        } else if self.0.packet_type == sys::zx_packet_type_t::ZX_PKT_TYPE_GUEST_VCPU {
+            PacketContents::GuestVcpu(GuestVcpuPacket(unsafe { mem::transmute_copy(&self.0.union) }))
         } else {
             panic!("unexpected packet type");
         }
@@ -122,10 +208,18 @@ impl Port {
     /// [zx_port_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_create.md)
     /// syscall.
     pub fn create() -> Result<Port, Status> {
+        Port::create_with_opts(PortOpts::Default)
+    }
+
+    /// Create an IO port with the given options.
+    ///
+    /// Wraps the
+    /// [zx_port_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/port_create.md)
+    /// syscall.
+    pub fn create_with_opts(opts: PortOpts) -> Result<Port, Status> {
         unsafe {
             let mut handle = 0;
-            let opts = 0;
-            let status = sys::zx_port_create(opts, &mut handle);
+            let status = sys::zx_port_create(opts as u32, &mut handle);
             ok(status)?;
             Ok(Handle::from_raw(handle).into())
         }
@@ -180,6 +274,16 @@ pub enum WaitAsyncOpts {
     Repeating = sys::ZX_WAIT_ASYNC_REPEATING,
 }
 
+/// Options for `Port::create_with_opts`.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PortOpts {
+    /// The default port, suitable for `object_wait_async` and `port_queue`.
+    Default = sys::ZX_PORT_OPT_DEFAULT,
+    /// A port that can additionally be bound to interrupts via `Interrupt::bind`.
+    BindToInterrupt = sys::ZX_PORT_OPT_BIND_TO_INTERRUPT,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,93 @@
+// Copyright 2018 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Type-safe bindings for Zircon timer objects.
+
+use {AsHandleRef, HandleBased, Handle, Port, Signals, Status, Time};
+use {sys, ok};
+
+/// An object representing a Zircon
+/// [timer](https://fuchsia.googlesource.com/zircon/+/master/docs/objects/timer.md).
+///
+/// As essentially a subtype of `Handle`, it can be freely interconverted.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Timer(Handle);
+impl_handle_based!(Timer);
+
+impl Timer {
+    /// Create a timer, an object that can be used to asynchronously signal when a deadline is
+    /// reached.
+    ///
+    /// Wraps the
+    /// [zx_timer_create](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/timer_create.md)
+    /// syscall.
+    pub fn create(clock_id: u32) -> Result<Timer, Status> {
+        unsafe {
+            let mut handle = 0;
+            let opts = 0;
+            let status = sys::zx_timer_create(opts, clock_id, &mut handle);
+            ok(status)?;
+            Ok(Handle::from_raw(handle).into())
+        }
+    }
+
+    /// Start a one-shot timer that will fire when `deadline` passes, asserting `TIMER_SIGNALED`
+    /// on this object until it is cancelled or reset.
+    ///
+    /// Wraps the
+    /// [zx_timer_set](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/timer_set.md)
+    /// syscall.
+    pub fn set(&self, deadline: Time, slack: Time) -> Result<(), Status> {
+        let status = unsafe {
+            sys::zx_timer_set(self.raw_handle(), deadline.nanos(), slack.nanos())
+        };
+        ok(status)
+    }
+
+    /// Cancel a pending timer that was started with `set`.
+    ///
+    /// Wraps the
+    /// [zx_timer_cancel](https://fuchsia.googlesource.com/zircon/+/master/docs/syscalls/timer_cancel.md)
+    /// syscall.
+    pub fn cancel(&self) -> Result<(), Status> {
+        let status = unsafe { sys::zx_timer_cancel(self.raw_handle()) };
+        ok(status)
+    }
+}
+
+/// The signal asserted by a `Timer` when it fires. Mirrors the signal observed in
+/// `ZX_PKT_TYPE_SIGNAL_*` packets delivered by a timer bound to a port via `wait_async`, just
+/// like the `Event`-based waits exercised in the `port` module's `wait_async_once` test.
+pub const TIMER_SIGNALED: Signals = Signals::USER_0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {DurationNum, PacketContents, WaitAsyncOpts};
+
+    #[test]
+    fn timer_basic() {
+        let ten_ms = 10.millis();
+        let key = 42;
+
+        let port = Port::create().unwrap();
+        let timer = Timer::create(0).unwrap();
+
+        assert!(timer.wait_async_handle(&port, key, TIMER_SIGNALED, WaitAsyncOpts::Once).is_ok());
+
+        // Waiting before the timer is armed should time out.
+        assert_eq!(port.wait(ten_ms.after_now()), Err(Status::TIMED_OUT));
+
+        // Arm the timer for the near future; we should get a signal packet once it fires.
+        assert!(timer.set(ten_ms.after_now(), 0.millis()).is_ok());
+        let read_packet = port.wait((10 * ten_ms).after_now()).unwrap();
+        assert_eq!(read_packet.key(), key);
+        match read_packet.contents() {
+            PacketContents::SignalOne(sig) => {
+                assert!(sig.observed().contains(TIMER_SIGNALED));
+            }
+            _ => panic!("wrong packet type"),
+        }
+    }
+}